@@ -1,18 +1,53 @@
 use std::fmt::Debug;
-use std::{collections::HashMap, env};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+};
 
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use git2::{Error, Repository, Time};
+use rayon::prelude::*;
 use rusqlite::{Connection, Result};
+use serde_json::Value;
 
 use regex::Regex;
 
+// Defaults for the git-hours style effort estimator, see `estimate_hours`.
+const DEFAULT_MAX_COMMIT_DIFF_MINUTES: i64 = 120;
+const DEFAULT_FIRST_COMMIT_ADDITION_MINUTES: i64 = 120;
+
+// Similarity percentage (0-100) above which git2's `find_similar` will match
+// an add/delete pair as a rename or copy instead of leaving them separate.
+const DEFAULT_RENAME_SIMILARITY_THRESHOLD: u16 = 50;
+
+// Default percentage change in a metric, relative to its previous value,
+// above which `compute_metric_regressions` flags a commit as a regression.
+const DEFAULT_REGRESSION_THRESHOLD_PERCENT: f64 = 10.0;
+
+// Unchanged context lines make `commit_file_lines` balloon on real repos
+// (3 lines of context on each side of every hunk, by default), so they're
+// off unless a caller opts in.
+const CAPTURE_LINE_CHANGE_CONTEXT: bool = false;
+
 #[derive(Debug)]
 pub struct FileInfo {
     path: String,
     status: String,
     added_lines: Option<i32>,
     removed_lines: Option<i32>,
+    line_changes: Option<Vec<LineChange>>,
+    old_path: Option<String>,
+}
+
+// A single line touched by a commit, as seen by git2's diff line callback.
+// `op` mirrors `git2::DiffLine::origin()`: '+' for an added line, '-' for a
+// removed line, ' ' for unchanged context.
+#[derive(Debug)]
+pub struct LineChange {
+    op: char,
+    old_line: Option<u32>,
+    new_line: Option<u32>,
+    content: String,
 }
 
 #[derive(Debug)]
@@ -25,6 +60,35 @@ pub struct GitLogEntry {
     files: Vec<FileInfo>,
 }
 
+#[derive(Debug)]
+pub struct AuthorStats {
+    author_email: String,
+    author_name: String,
+    commit_count: i32,
+    estimated_hours: f64,
+}
+
+// Result of `estimate_hours`: the per-author breakdown plus the sum across
+// all authors, i.e. the project's total estimated effort.
+#[derive(Debug)]
+pub struct HoursEstimate {
+    per_author: Vec<AuthorStats>,
+    overall_hours: f64,
+}
+
+// The per-metric change between a commit and the previous commit that
+// carried a value for the same metric, as produced by
+// `compute_metric_regressions`.
+#[derive(Debug)]
+pub struct MetricDelta {
+    commit_id: String,
+    metric_name: String,
+    value: f64,
+    previous_value: Option<f64>,
+    delta_percent: Option<f64>,
+    is_regression: bool,
+}
+
 #[derive(Debug)]
 pub struct QueryResult {
     id: String,
@@ -39,10 +103,11 @@ pub struct QueryResult {
 }
 
 fn convert_git_time_to_datetime(git_time: &Time) -> DateTime<Utc> {
-    Utc.timestamp(
+    Utc.timestamp_opt(
         git_time.seconds() + i64::from(git_time.offset_minutes()) * 60,
         0,
     )
+    .unwrap()
 }
 
 fn process_numberstats(
@@ -56,52 +121,89 @@ fn process_numberstats(
     let stats = diff.stats()?;
     let format = git2::DiffStatsFormat::NUMBER;
     let buf = stats.to_buf(format, 80)?;
-    let numberstats = std::str::from_utf8(&*buf)
-        .unwrap_or_else(|_| "")
-        .to_string();
+    let numberstats = std::str::from_utf8(&buf).unwrap_or("").to_string();
     let lines = numberstats.trim().split("\n");
 
     for line in lines {
         let captures = re.captures(line);
 
-        match captures {
-            Some(caps) => {
-                let added = caps.get(1).map_or("", |m| m.as_str());
-                let removed = caps.get(2).map_or("", |m| m.as_str());
-                let path = caps.get(3).map_or("", |m| m.as_str());
-
-                let file_info = files_map.get(path);
-                match file_info {
-                    Some(fi) => {
-                        result.insert(
-                            path.to_string(),
-                            FileInfo {
-                                path: fi.path.to_string(),
-                                status: fi.status.to_string(),
-                                added_lines: Some(parse_int(added)),
-                                removed_lines: Some(parse_int(removed)),
-                            },
-                        );
-                    }
-                    None => {
-                    }
-                }
+        if let Some(caps) = captures {
+            let added = caps.get(1).map_or("", |m| m.as_str());
+            let removed = caps.get(2).map_or("", |m| m.as_str());
+            let path = caps.get(3).map_or("", |m| m.as_str());
+
+            let file_info = files_map.get(path);
+            if let Some(fi) = file_info {
+                result.insert(
+                    path.to_string(),
+                    FileInfo {
+                        path: fi.path.to_string(),
+                        status: fi.status.to_string(),
+                        added_lines: Some(parse_int(added)),
+                        removed_lines: Some(parse_int(removed)),
+                        line_changes: None,
+                        old_path: fi.old_path.clone(),
+                    },
+                );
             }
-            None => {
-            },
         }
     }
     Ok(result)
 }
 
+// Opt-in companion to `process_numberstats`: instead of just the aggregate
+// added/removed counts, walks every line of every hunk in the diff and
+// records its content (with trailing whitespace trimmed), keyed by path. This is driven by git2's
+// `Diff::foreach` line callback rather than the numberstat text format,
+// since numberstat throws away the actual content and old/new line numbers.
+//
+// Unchanged context lines (' ') are skipped unless `include_context` is set:
+// the default diff context is 3 lines on each side of every hunk, so storing
+// them for every file of every commit would dwarf the actual additions and
+// removals in the database.
+fn process_line_changes(
+    diff: &git2::Diff,
+    include_context: bool,
+) -> Result<HashMap<String, Vec<LineChange>>, Error> {
+    let mut result: HashMap<String, Vec<LineChange>> = HashMap::new();
+
+    diff.foreach(
+        &mut |_delta, _progress| true,
+        None,
+        None,
+        Some(&mut |delta, _hunk, line| {
+            let op = line.origin();
+            if op != '+' && op != '-' && !(op == ' ' && include_context) {
+                return true;
+            }
+
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let content = String::from_utf8_lossy(line.content()).trim_end().to_string();
+
+            result.entry(path).or_default().push(LineChange {
+                op,
+                old_line: line.old_lineno(),
+                new_line: line.new_lineno(),
+                content,
+            });
+            true
+        }),
+    )?;
+
+    Ok(result)
+}
+
 fn parse_int(input: &str) -> i32 {
-    match input.parse() {
-        Ok(number) => number,
-        Err(_) => -1,
-    }
+    input.parse().unwrap_or(-1)
 }
 
-fn get_diff_delta_status(delta: git2::DiffDelta) -> &str {
+fn get_diff_delta_status(delta: &git2::DiffDelta) -> &'static str {
     let status_string: &str = match delta.status() {
         git2::Delta::Added => "Added",
         git2::Delta::Unmodified => "Unmodified",
@@ -118,77 +220,424 @@ fn get_diff_delta_status(delta: git2::DiffDelta) -> &str {
     status_string
 }
 
-pub fn walk_history(git_repo_path: &str) -> Result<Vec<GitLogEntry>, Error> {
+// Builds the `GitLogEntry` for a single commit against an already-open
+// `Repository`. git2's `Repository` is not `Send`, so `walk_history` opens
+// one per rayon job (via `par_iter().map_init`) rather than sharing a
+// single handle or reopening one per commit.
+fn process_commit(
+    repo: &Repository,
+    oid: git2::Oid,
+    capture_line_changes: bool,
+    capture_line_change_context: bool,
+    rename_similarity_threshold: u16,
+) -> Result<GitLogEntry, Error> {
+    let commit = repo.find_commit(oid)?;
+    let message = commit
+        .summary_bytes()
+        .unwrap_or_else(|| commit.message_bytes());
+    let author_name = match commit.author().name() {
+        None => "<none>".to_string(),
+        Some(n) => n.to_string(),
+    };
+    let author_email = match commit.author().email() {
+        None => "<none>".to_string(),
+        Some(e) => e.to_string(),
+    };
+
+    // Ignore merge commits (2+ parents) because that's what 'git whatchanged' does.
+    // Ignore commit with 0 parents (initial commit) because there's nothing to diff against
+    let mut files_map: HashMap<String, FileInfo> = HashMap::new();
+
+    if commit.parent_count() == 1 {
+        let prev_commit = commit.parent(0)?;
+        let tree = commit.tree()?;
+        let prev_tree = prev_commit.tree()?;
+        let mut diff = repo.diff_tree_to_tree(Some(&prev_tree), Some(&tree), None)?;
+
+        let mut find_opts = git2::DiffFindOptions::new();
+        find_opts
+            .renames(true)
+            .copies(true)
+            .rename_threshold(rename_similarity_threshold)
+            .copy_threshold(rename_similarity_threshold);
+        diff.find_similar(Some(&mut find_opts))?;
+
+        for delta in diff.deltas() {
+            let file_path = String::from(delta.new_file().path().unwrap().to_string_lossy());
+            let s_slice: &str = &file_path[..];
+            let status_string = get_diff_delta_status(&delta);
+            let old_path = match delta.status() {
+                git2::Delta::Renamed | git2::Delta::Copied => delta
+                    .old_file()
+                    .path()
+                    .map(|p| p.to_string_lossy().to_string()),
+                _ => None,
+            };
+
+            files_map.insert(
+                s_slice.to_string(),
+                FileInfo {
+                    status: status_string.to_string(),
+                    path: s_slice.to_string(),
+                    added_lines: None,
+                    removed_lines: None,
+                    line_changes: None,
+                    old_path,
+                },
+            );
+        }
+        let new_files_map = process_numberstats(&diff, &files_map).unwrap();
+        for f in new_files_map.values() {
+            files_map.insert( f.path.to_string(), FileInfo {
+                path: f.path.to_string(),
+                status: f.status.to_string(),
+                added_lines: f.added_lines,
+                removed_lines: f.removed_lines,
+                line_changes: None,
+                old_path: f.old_path.clone(),
+            });
+        }
+
+        if capture_line_changes {
+            let mut line_changes_map =
+                process_line_changes(&diff, capture_line_change_context).unwrap();
+            for (path, f) in files_map.iter_mut() {
+                if let Some(lines) = line_changes_map.remove(path) {
+                    f.line_changes = Some(lines);
+                }
+            }
+        }
+    }
+
+    Ok(GitLogEntry {
+        id: commit.id().to_string(),
+        summary: String::from_utf8_lossy(message).to_string(),
+        author_name,
+        author_email,
+        author_when: convert_git_time_to_datetime(&commit.time()),
+        files: Vec::from_iter(files_map.values().map(|f| FileInfo {
+            path: f.path.clone(),
+            status: f.status.clone(),
+            added_lines: f.added_lines,
+            removed_lines: f.removed_lines,
+            line_changes: f.line_changes.as_ref().map(|lines| {
+                lines
+                    .iter()
+                    .map(|l| LineChange {
+                        op: l.op,
+                        old_line: l.old_line,
+                        new_line: l.new_line,
+                        content: l.content.clone(),
+                    })
+                    .collect()
+            }),
+            old_path: f.old_path.clone(),
+        })),
+    })
+}
+
+// Walks commits reachable from `refs` (an empty slice means "just HEAD",
+// matching the old hardcoded behavior), skipping anything in
+// `known_commit_ids` (and its ancestors) so repeated runs against a
+// persistent database only pay for the commits that landed since the last
+// run. Pass an empty `known_commit_ids` for a full, from-scratch walk.
+//
+// Each entry in `refs` is either a glob (e.g. `refs/tags/*`), pushed via
+// `push_glob`, or a single revspec (a branch name, tag name, or OID). The
+// latter is resolved with `revparse_single`/`peel_to_commit` rather than
+// `push_ref`, since `push_ref` only accepts fully-qualified ref names and
+// won't dwim short branch/tag names or raw OIDs.
+pub fn walk_history(
+    git_repo_path: &str,
+    capture_line_changes: bool,
+    capture_line_change_context: bool,
+    known_commit_ids: &HashSet<String>,
+    rename_similarity_threshold: u16,
+    refs: &[String],
+) -> Result<Vec<GitLogEntry>, Error> {
     let repo = Repository::open(git_repo_path)?;
-    let mut vec: Vec<GitLogEntry> = Vec::new();
     let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
+    if refs.is_empty() {
+        revwalk.push_head()?;
+    } else {
+        for r in refs {
+            if r.contains('*') {
+                revwalk.push_glob(r)?;
+            } else {
+                let oid = repo.revparse_single(r)?.peel_to_commit()?.id();
+                revwalk.push(oid)?;
+            }
+        }
+    }
+    for known_id in known_commit_ids {
+        if let Ok(oid) = git2::Oid::from_str(known_id) {
+            // A known commit may have been pruned/GC'd since it was recorded;
+            // that just means there's nothing left to hide for it.
+            let _ = revwalk.hide(oid);
+        }
+    }
     let _ = revwalk.set_sorting(git2::Sort::TIME | git2::Sort::REVERSE);
-    for rev in revwalk {
-        let commit = repo.find_commit(rev?)?;
-        let message = commit
-            .summary_bytes()
-            .unwrap_or_else(|| commit.message_bytes());
-        let author_name = match commit.author().name() {
-            None => "<none>".to_string(),
-            Some(n) => n.to_string(),
-        };
-        let author_email = match commit.author().email() {
-            None => "<none>".to_string(),
-            Some(e) => e.to_string(),
-        };
-
-        // Ignore merge commits (2+ parents) because that's what 'git whatchanged' does.
-        // Ignore commit with 0 parents (initial commit) because there's nothing to diff against
-        let mut files_map: HashMap<String, FileInfo> = HashMap::new();
-
-        if commit.parent_count() == 1 {
-            let prev_commit = commit.parent(0)?;
-            let tree = commit.tree()?;
-            let prev_tree = prev_commit.tree()?;
-            let diff = repo.diff_tree_to_tree(Some(&prev_tree), Some(&tree), None)?;
-
-            for delta in diff.deltas() {
-                let file_path = String::from(delta.new_file().path().unwrap().to_string_lossy());
-                let s_slice: &str = &file_path[..];
-                let status_string = get_diff_delta_status(delta);
-
-                files_map.insert(
-                    s_slice.to_string(),
-                    FileInfo {
-                        status: status_string.to_string(),
-                        path: s_slice.to_string(),
-                        added_lines: None,
-                        removed_lines: None,
-                    },
-                );
+
+    // Collecting the OIDs up front lets the (comparatively cheap) revwalk stay
+    // single-threaded while the expensive part - the tree-to-tree diff and
+    // numberstat parsing per commit - runs in parallel below.
+    let oids: Vec<git2::Oid> = revwalk.collect::<Result<Vec<_>, Error>>()?;
+
+    // map_init opens one `Repository` per rayon job (the closure runs once for
+    // each group of items rayon splits off, not once per item), instead of
+    // paying `Repository::open` on every single commit.
+    let mut entries: Vec<GitLogEntry> = oids
+        .par_iter()
+        .map_init(
+            || Repository::open(git_repo_path),
+            |repo, oid| {
+                let repo = repo
+                    .as_ref()
+                    .map_err(|e| git2::Error::from_str(e.message()))?;
+                process_commit(
+                    repo,
+                    *oid,
+                    capture_line_changes,
+                    capture_line_change_context,
+                    rename_similarity_threshold,
+                )
+            },
+        )
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // Sort on author time (falling back to id to break ties) so the output
+    // order is deterministic; this can differ from the revwalk's order, which
+    // is by committer time.
+    entries.sort_by(|a, b| a.author_when.cmp(&b.author_when).then_with(|| a.id.cmp(&b.id)));
+
+    Ok(entries)
+}
+
+// Derives an approximate time-on-repo figure per author using the git-hours
+// heuristic (https://github.com/kimmobrunfeldt/git-hours, as also described
+// in the gitoxide docs): walk each author's commits in chronological order
+// and, for every consecutive pair, count the real gap towards their total if
+// it's below `max_commit_diff`, otherwise assume `first_commit_addition` of
+// work led up to the (presumably isolated) commit. The same addition is
+// applied before an author's very first commit.
+pub fn estimate_hours(
+    entries: &[GitLogEntry],
+    max_commit_diff_minutes: i64,
+    first_commit_addition_minutes: i64,
+) -> HoursEstimate {
+    let max_commit_diff = Duration::minutes(max_commit_diff_minutes);
+    let first_commit_addition = Duration::minutes(first_commit_addition_minutes);
+
+    let mut by_author: HashMap<String, (String, Vec<DateTime<Utc>>)> = HashMap::new();
+    for entry in entries {
+        let author = by_author
+            .entry(entry.author_email.clone())
+            .or_insert_with(|| (entry.author_name.clone(), Vec::new()));
+        author.1.push(entry.author_when);
+    }
+
+    let mut stats: Vec<AuthorStats> = Vec::new();
+    for (author_email, (author_name, mut author_when)) in by_author {
+        author_when.sort();
+
+        let mut total = Duration::zero();
+        if !author_when.is_empty() {
+            total += first_commit_addition;
+        }
+        for window in author_when.windows(2) {
+            let gap = window[1] - window[0];
+            if gap < max_commit_diff {
+                total += gap;
+            } else {
+                total += first_commit_addition;
             }
-            let new_files_map = process_numberstats(&diff, &files_map).unwrap();
-            for f in new_files_map.values() {
-                files_map.insert( f.path.to_string(), FileInfo {
-                    path: f.path.to_string(),
-                    status: f.status.to_string(),
-                    added_lines: f.added_lines,
-                    removed_lines: f.removed_lines,
-                });
+        }
+
+        stats.push(AuthorStats {
+            author_email,
+            author_name,
+            commit_count: author_when.len() as i32,
+            estimated_hours: total.num_seconds() as f64 / 3600.0,
+        });
+    }
+
+    let overall_hours = stats.iter().map(|s| s.estimated_hours).sum();
+
+    HoursEstimate {
+        per_author: stats,
+        overall_hours,
+    }
+}
+
+// Loads named performance measurements (benchmark timings, binary size, etc)
+// from a simple JSON file shaped as `{ "<commit hash>": { "<metric>": value } }`,
+// so results can be accumulated across separate runs of the tool.
+fn load_metrics_file(path: &str) -> std::io::Result<HashMap<String, HashMap<String, f64>>> {
+    let contents = std::fs::read_to_string(path)?;
+    let json: Value = serde_json::from_str(&contents)?;
+
+    let mut metrics_by_commit: HashMap<String, HashMap<String, f64>> = HashMap::new();
+    if let Value::Object(commits) = json {
+        for (commit_id, metrics) in commits {
+            if let Value::Object(named_values) = metrics {
+                let values = named_values
+                    .into_iter()
+                    .filter_map(|(name, value)| value.as_f64().map(|v| (name, v)))
+                    .collect();
+                metrics_by_commit.insert(commit_id, values);
             }
         }
-        vec.push(GitLogEntry {
-            id: commit.id().to_string(),
-            summary: String::from_utf8_lossy(message).to_string(),
-            author_name: author_name,
-            author_email: author_email,
-            author_when: convert_git_time_to_datetime(&commit.time()),
-            files: Vec::from_iter(files_map.values().map(|f| FileInfo {
-                path: f.path.clone(),
-                status: f.status.clone(),
-                added_lines: f.added_lines,
-                removed_lines: f.removed_lines,
-            })),
+    }
+
+    Ok(metrics_by_commit)
+}
+
+// Walking commits in chronological order, computes the delta between each
+// metric's value and its value at the previous commit that recorded the same
+// metric, flagging a commit as a regression when a metric *grows* by more
+// than `threshold_percent` (larger-is-worse: timings, binary size, etc). A
+// shrink of the same or greater magnitude is an improvement, not a
+// regression, and is never flagged. Inspired by the bisect-perf-regressions
+// style of tooling: this doesn't run benchmarks itself, it just reports on
+// values already loaded into `commit_metrics` (see `load_metrics_file`).
+fn compute_metric_regressions(
+    conn: &Connection,
+    threshold_percent: f64,
+) -> Result<Vec<MetricDelta>> {
+    let mut stmt = conn.prepare(
+        "SELECT commit_metrics.commit_id, commit_metrics.metric_name, commit_metrics.value \
+         FROM commit_metrics INNER JOIN commits ON commit_metrics.commit_id = commits.id \
+         ORDER BY commit_metrics.metric_name, commits.author_when",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut previous_by_metric: HashMap<String, f64> = HashMap::new();
+    let mut deltas = Vec::new();
+    for (commit_id, metric_name, value) in rows {
+        let previous_value = previous_by_metric.get(&metric_name).copied();
+        // No delta is reported off a zero baseline: there's no comparable
+        // percentage change to measure, so leave it as "no baseline" (None)
+        // rather than claiming a 0% change.
+        let delta_percent = previous_value.and_then(|previous| {
+            if previous == 0.0 {
+                None
+            } else {
+                Some((value - previous) / previous.abs() * 100.0)
+            }
+        });
+        // Treat larger-as-worse (the common case for timings, binary size,
+        // etc): only a growth beyond the threshold counts as a regression,
+        // not an improvement of the same magnitude.
+        let is_regression = delta_percent.is_some_and(|pct| pct > threshold_percent);
+
+        deltas.push(MetricDelta {
+            commit_id,
+            metric_name: metric_name.clone(),
+            value,
+            previous_value,
+            delta_percent,
+            is_regression,
         });
+        previous_by_metric.insert(metric_name, value);
     }
 
-    return Ok(vec);
+    Ok(deltas)
+}
+
+// Enumerates the tips worth tracking reachability from: every local branch
+// and tag (via `repo.branches()`/`repo.tag_names()`), plus the current HEAD
+// labeled with `repo.describe()` so it reads as something like
+// "v1.2.0-3-gabcd123" rather than the bare word "HEAD".
+fn collect_ref_tips(repo: &Repository) -> Result<Vec<(String, String, git2::Oid)>, Error> {
+    let mut tips = Vec::new();
+
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _branch_type) = branch?;
+        if let (Some(name), Some(target)) = (branch.name()?, branch.get().target()) {
+            tips.push((name.to_string(), "branch".to_string(), target));
+        }
+    }
+
+    for tag_name in repo.tag_names(None)?.iter().flatten() {
+        if let Ok(obj) = repo.revparse_single(&format!("refs/tags/{}", tag_name)) {
+            if let Ok(commit) = obj.peel_to_commit() {
+                tips.push((tag_name.to_string(), "tag".to_string(), commit.id()));
+            }
+        }
+    }
+
+    if let Ok(head) = repo.head() {
+        if let Some(target) = head.target() {
+            let mut describe_opts = git2::DescribeOptions::new();
+            describe_opts.describe_tags();
+            let label = repo
+                .describe(&describe_opts)
+                .and_then(|d| d.format(None))
+                .unwrap_or_else(|_| "HEAD".to_string());
+            tips.push((label, "head".to_string(), target));
+        }
+    }
+
+    Ok(tips)
+}
+
+// For every branch/tag/HEAD tip, walks everything reachable from it and
+// records the (commit, ref) pairs so users can see which releases or
+// branches a given commit shipped in, rather than only ever seeing whatever
+// happens to be checked out.
+fn compute_ref_membership(git_repo_path: &str) -> Result<Vec<(String, String, String)>, Error> {
+    let repo = Repository::open(git_repo_path)?;
+    let tips = collect_ref_tips(&repo)?;
+
+    let mut rows = Vec::new();
+    for (ref_name, ref_type, oid) in tips {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(oid)?;
+        for rev in revwalk {
+            rows.push((rev?.to_string(), ref_name.clone(), ref_type.clone()));
+        }
+    }
+
+    Ok(rows)
+}
+
+// Reads back the commit IDs already persisted in `commits`, so `walk_history`
+// knows which part of the history it can skip on this run.
+fn load_known_commit_ids(conn: &Connection) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare("SELECT id FROM commits")?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<HashSet<String>, _>>()?;
+    Ok(ids)
+}
+
+// Reloads every persisted commit (not just the ones from this run) so
+// `estimate_hours` sees an author's full history rather than just the
+// newly-walked top-up range.
+fn load_all_commits(conn: &Connection) -> Result<Vec<GitLogEntry>> {
+    let mut stmt =
+        conn.prepare("SELECT id, summary, author_name, author_email, author_when FROM commits")?;
+    let entries = stmt
+        .query_map([], |row| {
+            Ok(GitLogEntry {
+                id: row.get(0)?,
+                summary: row.get(1)?,
+                author_name: row.get(2)?,
+                author_email: row.get(3)?,
+                author_when: row.get(4)?,
+                files: Vec::new(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
 }
 
 fn main() -> Result<()> {
@@ -196,7 +645,13 @@ fn main() -> Result<()> {
     let repo_path = &args[1];
     println!("Analyzing Git repository at {:?}", repo_path);
 
-    let conn = Connection::open_in_memory()?;
+    // A db path is optional: pass one to analyze incrementally across runs
+    // (e.g. `git-analyzer <repo> <db-file>`), otherwise fall back to a
+    // scratch in-memory database that starts from scratch every time.
+    let conn = match args.get(2) {
+        Some(db_path) => Connection::open(db_path)?,
+        None => Connection::open_in_memory()?,
+    };
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS commits (
@@ -215,12 +670,72 @@ fn main() -> Result<()> {
             name  TEXT,
             status  TEXT,
             added INT,
-            deleted INT
+            deleted INT,
+            old_path TEXT
             );",
         (), // empty list of parameters.
     )?;
 
-    let commits = walk_history(repo_path).unwrap();
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS author_stats (
+            author_email	TEXT UNIQUE,
+            author_name	TEXT,
+            commit_count	INT,
+            estimated_hours	REAL
+            );",
+        (), // empty list of parameters.
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS commit_file_lines (
+            commit_id	TEXT,
+            path	TEXT,
+            op	TEXT,
+            old_line	INT,
+            new_line	INT,
+            content	TEXT
+            );",
+        (), // empty list of parameters.
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS commit_metrics (
+            commit_id	TEXT,
+            metric_name	TEXT,
+            value	REAL,
+            UNIQUE(commit_id, metric_name)
+            );",
+        (), // empty list of parameters.
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS refs (
+            commit_id	TEXT,
+            ref_name	TEXT,
+            ref_type	TEXT,
+            UNIQUE(commit_id, ref_name)
+            );",
+        (), // empty list of parameters.
+    )?;
+
+    // A comma-separated list of revspecs/globs is optional (e.g.
+    // `git-analyzer <repo> <db-file> <metrics-file> refs/tags/*,main`); with
+    // none given, the walk falls back to just HEAD's history as before.
+    let refs: Vec<String> = match args.get(4) {
+        Some(refs_csv) => refs_csv.split(',').map(|r| r.trim().to_string()).collect(),
+        None => Vec::new(),
+    };
+
+    let known_commit_ids = load_known_commit_ids(&conn)?;
+    let commits = walk_history(
+        repo_path,
+        true,
+        CAPTURE_LINE_CHANGE_CONTEXT,
+        &known_commit_ids,
+        DEFAULT_RENAME_SIMILARITY_THRESHOLD,
+        &refs,
+    )
+    .unwrap();
 
     for commit in commits {
         let s_slice: &str = &commit.id[..];
@@ -229,13 +744,87 @@ fn main() -> Result<()> {
             (s_slice, commit.summary, commit.author_name, commit.author_email, commit.author_when),
         )?;
         for file in commit.files {
+            if let Some(line_changes) = &file.line_changes {
+                for line_change in line_changes {
+                    conn.execute(
+                        "INSERT INTO commit_file_lines (commit_id, path, op, old_line, new_line, content) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        (
+                            s_slice,
+                            &file.path,
+                            line_change.op.to_string(),
+                            line_change.old_line,
+                            line_change.new_line,
+                            &line_change.content,
+                        ),
+                    )?;
+                }
+            }
             conn.execute(
-                "INSERT INTO commit_files (id, name, status, added, deleted) VALUES (?1, ?2, ?3, ?4, ?5)",
-                (s_slice, file.path, file.status, file.added_lines, file.removed_lines),
+                "INSERT INTO commit_files (id, name, status, added, deleted, old_path) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                (s_slice, file.path, file.status, file.added_lines, file.removed_lines, file.old_path),
             )?;
         }
     }
 
+    // Recompute author stats from the full persisted history (not just this
+    // run's top-up range) so incremental runs still report accurate totals.
+    let all_commits = load_all_commits(&conn)?;
+    let hours_estimate = estimate_hours(
+        &all_commits,
+        DEFAULT_MAX_COMMIT_DIFF_MINUTES,
+        DEFAULT_FIRST_COMMIT_ADDITION_MINUTES,
+    );
+    for stats in hours_estimate.per_author {
+        conn.execute(
+            "INSERT OR REPLACE INTO author_stats (author_email, author_name, commit_count, estimated_hours) VALUES (?1, ?2, ?3, ?4)",
+            (stats.author_email, stats.author_name, stats.commit_count, stats.estimated_hours),
+        )?;
+    }
+    println!(
+        "overall estimated hours across all authors: {:?}",
+        hours_estimate.overall_hours
+    );
+
+    // A metrics file is optional: pass one to associate named measurements
+    // (benchmark timings, binary size, ...) with the commits that produced
+    // them (e.g. `git-analyzer <repo> <db-file> <metrics-file>`).
+    if let Some(metrics_path) = args.get(3) {
+        let metrics_by_commit = load_metrics_file(metrics_path).unwrap();
+        for (commit_id, metrics) in metrics_by_commit {
+            for (metric_name, value) in metrics {
+                conn.execute(
+                    "INSERT OR REPLACE INTO commit_metrics (commit_id, metric_name, value) VALUES (?1, ?2, ?3)",
+                    (&commit_id, &metric_name, value),
+                )?;
+            }
+        }
+    }
+
+    for (commit_id, ref_name, ref_type) in compute_ref_membership(repo_path).unwrap() {
+        conn.execute(
+            "INSERT OR REPLACE INTO refs (commit_id, ref_name, ref_type) VALUES (?1, ?2, ?3)",
+            (commit_id, ref_name, ref_type),
+        )?;
+    }
+
+    let metric_regressions: Vec<MetricDelta> = compute_metric_regressions(
+        &conn,
+        DEFAULT_REGRESSION_THRESHOLD_PERCENT,
+    )?
+    .into_iter()
+    .filter(|delta| delta.is_regression)
+    .collect();
+    for regression in metric_regressions {
+        println!(
+            "regression: {:?}\t{:?}\t{:?}\t{:?}\t{:?}%",
+            regression.commit_id,
+            regression.metric_name,
+            regression.previous_value,
+            regression.value,
+            regression.delta_percent,
+        );
+    }
+
     let mut stmt = conn.prepare("SELECT commits.id, commits.summary, commits.author_name, commits.author_email, commits.author_when, commit_files.name, commit_files.status, commit_files.added, commit_files.deleted FROM commits INNER JOIN commit_files ON commits.id=commit_files.id")?;
     let commit_iter = stmt.query_map([], |row| {
         Ok(QueryResult {